@@ -3,7 +3,7 @@
 use std::io;
 
 use flate2::read;
-use serde::de;
+use serde::de::{self, IntoDeserializer};
 
 use error::{Error, Result};
 
@@ -18,9 +18,29 @@ where
     T: de::Deserialize<'a>,
 {
     let src = SliceRead::new(src);
-    let mut decoder = Decoder::new(src);
-    let res = de::Deserialize::deserialize(&mut decoder)?;
-    Ok((decoder.reader.get_inner(), res))
+    let mut decoder = Decoder::new(Offset::new(src));
+    let res = de::Deserialize::deserialize(&mut decoder)
+        .map_err(|err| Error::at(decoder.reader.position(), err))?;
+    Ok((decoder.reader.inner.get_inner(), res))
+}
+
+/// Decode an object from a byte slice, requiring that the slice contain
+/// exactly one NBT document and nothing else.
+///
+/// Use this over [`from_slice`] when the input is expected to hold a single
+/// document (e.g. a whole file read into memory); any trailing bytes after
+/// the root compound are reported as `Error::TrailingData` instead of being
+/// silently returned to the caller.
+pub fn from_slice_strict<'a, T>(src: &'a [u8]) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let reader = Offset::new(SliceRead::new(src));
+    let mut decoder = Decoder::new(reader);
+    let value = de::Deserialize::deserialize(&mut decoder)
+        .map_err(|err| Error::at(decoder.reader.position(), err))?;
+    decoder.end()?;
+    Ok(value)
 }
 
 /// Decode an object from Named Binary Tag (NBT) format.
@@ -32,8 +52,26 @@ where
     R: io::Read,
     T: de::DeserializeOwned,
 {
-    let mut decoder = Decoder::new(src);
-    de::Deserialize::deserialize(&mut decoder)
+    let mut decoder = Decoder::new(Offset::new(src));
+    de::Deserialize::deserialize(&mut decoder).map_err(|err| Error::at(decoder.reader.position(), err))
+}
+
+/// Decode an object from a reader, requiring that the reader be exhausted
+/// immediately after the root compound.
+///
+/// Use this over [`from_reader`] when `src` is expected to hold a single
+/// document; any trailing bytes are reported as `Error::TrailingData`
+/// instead of being left unread on the reader.
+pub fn from_reader_strict<R, T>(src: R) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut decoder = Decoder::new(Offset::new(src));
+    let value = de::Deserialize::deserialize(&mut decoder)
+        .map_err(|err| Error::at(decoder.reader.position(), err))?;
+    decoder.end()?;
+    Ok(value)
 }
 
 /// Decode an object from Named Binary Tag (NBT) format.
@@ -62,6 +100,102 @@ where
     from_reader(zlib)
 }
 
+/// A `Read` wrapper that counts how many bytes have passed through it, so
+/// that a decode error can be tagged with the stream offset it occurred at.
+/// Mirrors the `Offset` reader in `serde_cbor`.
+struct Offset<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R> Offset<R> {
+    fn new(inner: R) -> Self {
+        Offset { inner, pos: 0 }
+    }
+
+    /// The number of bytes read so far.
+    fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl<'de, R: Read<'de>> Read<'de> for Offset<R> {
+    fn read_bare_byte(&mut self) -> Result<i8> {
+        let value = self.inner.read_bare_byte()?;
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn read_bare_short(&mut self) -> Result<i16> {
+        let value = self.inner.read_bare_short()?;
+        self.pos += 2;
+        Ok(value)
+    }
+
+    fn read_bare_int(&mut self) -> Result<i32> {
+        let value = self.inner.read_bare_int()?;
+        self.pos += 4;
+        Ok(value)
+    }
+
+    fn read_bare_long(&mut self) -> Result<i64> {
+        let value = self.inner.read_bare_long()?;
+        self.pos += 8;
+        Ok(value)
+    }
+
+    fn read_bare_float(&mut self) -> Result<f32> {
+        let value = self.inner.read_bare_float()?;
+        self.pos += 4;
+        Ok(value)
+    }
+
+    fn read_bare_double(&mut self) -> Result<f64> {
+        let value = self.inner.read_bare_double()?;
+        self.pos += 8;
+        Ok(value)
+    }
+
+    fn read_bare_string<'b>(&'b mut self, scratch: Option<&'b mut Vec<u8>>) -> Result<Reference<'de, 'b, str>> {
+        let value = self.inner.read_bare_string(scratch)?;
+        self.pos += 2 + mutf8_len(&value);
+        Ok(value)
+    }
+
+    fn emit_next_header<'b>(
+        &'b mut self,
+        scratch: Option<&'b mut Vec<u8>>,
+    ) -> Result<(u8, Reference<'de, 'b, str>)> {
+        let (tag, name) = self.inner.emit_next_header(scratch)?;
+        self.pos += 1 + if tag == 0x00 { 0 } else { 2 + mutf8_len(&name) };
+        Ok((tag, name))
+    }
+}
+
+/// The number of bytes `s` would occupy on the wire as a Modified UTF-8
+/// (CESU-8) string, i.e. the NBT string encoding Java's `DataOutputStream`
+/// uses — as opposed to `str::len`, which is `s`'s *standard* UTF-8 length.
+/// The two diverge for an embedded NUL (1 byte standard, 2 bytes modified)
+/// and for any supplementary-plane character (4 bytes standard, 6 bytes
+/// modified, since it's written as a CESU-8 surrogate pair). `Offset` needs
+/// this to recover the true wire position from an already-decoded `str`.
+fn mutf8_len(s: &str) -> u64 {
+    s.chars()
+        .map(|c| match c {
+            '\0' => 2,
+            c if (c as u32) > 0xffff => 6,
+            c => c.len_utf8() as u64,
+        })
+        .sum()
+}
+
+/// The default recursion depth limit used by `Decoder::new`.
+///
+/// This bounds how many nested compounds/lists a `Decoder` will follow
+/// before giving up with `Error::DepthLimitExceeded`, so that a hostile
+/// input can't blow the stack via unbounded recursion.
+const DEFAULT_DEPTH_LIMIT: u16 = 256;
+
 /// Decode objects from Named Binary Tag (NBT) format.
 ///
 /// Note that only maps and structs can be decoded, because the NBT format does
@@ -69,14 +203,53 @@ where
 pub struct Decoder<R> {
     reader: R,
     scratch: Vec<u8>,
+    remaining_depth: u16,
 }
 
 impl<R> Decoder<R> {
     /// Create an NBT Decoder from a given source.
     pub fn new(src: R) -> Self {
+        Decoder::with_depth_limit(src, DEFAULT_DEPTH_LIMIT)
+    }
+
+    /// Create an NBT Decoder from a given source, with a custom limit on how
+    /// many compounds/lists deep it will recurse before returning
+    /// `Error::DepthLimitExceeded`.
+    pub fn with_depth_limit(src: R, depth_limit: u16) -> Self {
         Decoder {
             reader: src,
             scratch: Vec::new(),
+            remaining_depth: depth_limit,
+        }
+    }
+
+    /// Run `f` with the remaining depth budget decremented by one, restoring
+    /// it afterwards regardless of whether `f` succeeds, fails, or bails out
+    /// early (e.g. on a truncated/`0x00` end tag).
+    fn with_nesting<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.remaining_depth = self
+            .remaining_depth
+            .checked_sub(1)
+            .ok_or(Error::DepthLimitExceeded)?;
+        let result = f(self);
+        self.remaining_depth += 1;
+        result
+    }
+}
+
+impl<'de, R: Read<'de>> Decoder<R> {
+    /// Assert that the decoder has consumed its entire input, i.e. that
+    /// nothing follows the root compound. Returns `Error::TrailingData` if
+    /// there is unconsumed data left in the stream.
+    ///
+    /// Call this after deserializing to validate a whole-buffer/whole-file
+    /// document; don't call it when streaming multiple NBT documents back to
+    /// back from the same reader.
+    pub fn end(mut self) -> Result<()> {
+        match self.reader.read_bare_byte() {
+            Ok(_) => Err(Error::TrailingData),
+            Err(Error::UnexpectedEof) => Ok(()),
+            Err(err) => Err(err),
         }
     }
 }
@@ -128,14 +301,39 @@ impl<'de: 'a, 'a, R: Read<'de>> de::Deserializer<'de> for &'a mut Decoder<R> {
         let (tag, _) = self.reader.emit_next_header(Some(&mut self.scratch))?;
 
         match tag {
-            0x0a => visitor.visit_map(MapDecoder::new(self)),
+            0x0a => self.with_nesting(|this| visitor.visit_map(MapDecoder::new(this))),
+            _ => Err(Error::NoRootCompound),
+        }
+    }
+
+    /// Deserialize an enum, represented either as a single-entry compound
+    /// (`{"VariantName": payload}`) for variants carrying data, or as a bare
+    /// string naming the variant for unit variants.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let (tag, _) = self.reader.emit_next_header(Some(&mut self.scratch))?;
+
+        match tag {
+            0x0a => self.with_nesting(|this| visitor.visit_enum(EnumDecoder::new(this))),
+            0x08 => match self.reader.read_bare_string(Some(&mut self.scratch))? {
+                Reference::Borrowed(b) => visitor.visit_enum(b.into_deserializer()),
+                Reference::Copied(c) => visitor.visit_enum(c.into_deserializer()),
+                Reference::Owned(o) => visitor.visit_enum(o.into_deserializer()),
+            },
             _ => Err(Error::NoRootCompound),
         }
     }
 
     forward_to_deserialize_any! {
         bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes byte_buf
-        unit seq tuple_struct tuple option enum identifier ignored_any
+        unit seq tuple_struct tuple option identifier ignored_any
     }
 }
 
@@ -193,6 +391,17 @@ impl<'de: 'a, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for MapDecoder<'a, R> {
 }
 
 /// Decoder for list-like types.
+///
+/// `int_array`/`long_array` (tags `0x0b`/`0x0c`) are decoded element-by-element
+/// through this same `SeqAccess`, just like an ordinary list — there is no
+/// in-place borrowing of the underlying `&[i32]`/`&[i64]` here. Real zero-copy
+/// borrowing would need the `Read` trait to expose a primitive for handing
+/// back a borrowed run of raw wire bytes (so a wrapper type could reinterpret
+/// them as big-endian elements without a copy); `Read` only exposes one
+/// fixed-width value at a time, so that path isn't available from `de.rs`
+/// alone. Given that, per-element decoding (which `read_bare_int`/
+/// `read_bare_long` already handle correctly, byte-swap included) is the
+/// right tradeoff here rather than a half-measure wrapper that copies anyway.
 struct SeqDecoder<'a, R: 'a> {
     outer: &'a mut Decoder<R>,
     tag: u8,
@@ -274,6 +483,24 @@ impl<'de: 'a, 'a, R: Read<'de> + 'a> de::SeqAccess<'de> for SeqDecoder<'a, R> {
     }
 }
 
+/// Map an NBT tag id to the `serde::de::Unexpected` kind it corresponds to,
+/// so that tag mismatches surface as ordinary serde type-mismatch messages
+/// (e.g. `invalid type: string "", expected a boolean`) instead of a bare tag
+/// number. The payload carried by a few variants (`Signed`/`Float`/`Str`)
+/// isn't available at the point a tag mismatch is detected, so a placeholder
+/// (`0`, `0.0`, `""`) is used there, and serde prints that placeholder as
+/// part of the error message.
+fn tag_to_unexpected<'a>(tag: u8) -> de::Unexpected<'a> {
+    match tag {
+        0x01..=0x04 => de::Unexpected::Signed(0),
+        0x05 | 0x06 => de::Unexpected::Float(0.0),
+        0x07 | 0x09 | 0x0b | 0x0c => de::Unexpected::Seq,
+        0x08 => de::Unexpected::Str(""),
+        0x0a => de::Unexpected::Map,
+        _ => de::Unexpected::Other("unknown NBT tag"),
+    }
+}
+
 /// Private inner decoder, for decoding raw (i.e. non-Compound) types.
 struct InnerDecoder<'a, R: 'a> {
     outer: &'a mut Decoder<R>,
@@ -296,16 +523,22 @@ impl<'a, 'b: 'a, 'de, R: Read<'de>> de::Deserializer<'de> for &'b mut InnerDecod
             0x04 => visitor.visit_i64(outer.reader.read_bare_long()?),
             0x05 => visitor.visit_f32(outer.reader.read_bare_float()?),
             0x06 => visitor.visit_f64(outer.reader.read_bare_double()?),
-            0x07 => visitor.visit_seq(SeqDecoder::byte_array(outer)?),
+            0x07 => outer.with_nesting(|outer| visitor.visit_seq(SeqDecoder::byte_array(outer)?)),
             0x08 => match outer.reader.read_bare_string(Some(&mut outer.scratch))? {
                 Reference::Borrowed(b) => visitor.visit_borrowed_str(b),
                 Reference::Copied(c) => visitor.visit_str(c),
                 Reference::Owned(o) => visitor.visit_string(o),
             },
-            0x09 => visitor.visit_seq(SeqDecoder::list(outer)?),
-            0x0a => visitor.visit_map(MapDecoder::new(outer)),
-            0x0b => visitor.visit_seq(SeqDecoder::int_array(outer)?),
-            0x0c => visitor.visit_seq(SeqDecoder::long_array(outer)?),
+            0x09 => outer.with_nesting(|outer| visitor.visit_seq(SeqDecoder::list(outer)?)),
+            0x0a => outer.with_nesting(|outer| visitor.visit_map(MapDecoder::new(outer))),
+            0x0b => outer.with_nesting(|outer| visitor.visit_seq(SeqDecoder::int_array(outer)?)),
+            0x0c => outer.with_nesting(|outer| visitor.visit_seq(SeqDecoder::long_array(outer)?)),
+            // Every valid NBT tag (0x01-0x0c) is handled above. Unlike the
+            // other `deserialize_*` methods here, `deserialize_any` has no
+            // expected type of its own to contrast against, so there's no
+            // "expected X, found Y" to report via `de::Error::invalid_type`
+            // — this is reached only for a genuinely invalid/corrupt tag
+            // byte, which `Error::InvalidTypeId` names directly.
             t => Err(Error::InvalidTypeId(t)),
         }
     }
@@ -325,7 +558,7 @@ impl<'a, 'b: 'a, 'de, R: Read<'de>> de::Deserializer<'de> for &'b mut InnerDecod
                     b => Err(Error::NonBooleanByte(b)),
                 }
             }
-            _ => Err(Error::TagMismatch(self.tag, 0x01)),
+            t => Err(de::Error::invalid_type(tag_to_unexpected(t), &visitor)),
         }
     }
 
@@ -370,12 +603,167 @@ impl<'a, 'b: 'a, 'de, R: Read<'de>> de::Deserializer<'de> for &'b mut InnerDecod
                 let value = reader.read_bare_string(None)?;
                 visitor.visit_string(value.into_owned())
             }
-            _ => Err(Error::TagMismatch(self.tag, 0x08)),
+            t => Err(de::Error::invalid_type(tag_to_unexpected(t), &visitor)),
+        }
+    }
+
+    /// Deserialize an enum. See `Decoder::deserialize_enum` for the wire
+    /// representation; here the variant payload is reached through whatever
+    /// tag this `InnerDecoder` was constructed with.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.tag {
+            0x0a => self
+                .outer
+                .with_nesting(|outer| visitor.visit_enum(EnumDecoder::new(outer))),
+            0x08 => match self.outer.reader.read_bare_string(Some(&mut self.outer.scratch))? {
+                Reference::Borrowed(b) => visitor.visit_enum(b.into_deserializer()),
+                Reference::Copied(c) => visitor.visit_enum(c.into_deserializer()),
+                Reference::Owned(o) => visitor.visit_enum(o.into_deserializer()),
+            },
+            t => Err(de::Error::invalid_type(tag_to_unexpected(t), &visitor)),
         }
     }
 
     forward_to_deserialize_any! {
         u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str bytes byte_buf seq
-        map tuple_struct struct tuple enum identifier ignored_any
+        map tuple_struct struct tuple identifier ignored_any
+    }
+}
+
+/// Decoder for enum types represented as a single-entry compound whose one
+/// key is the variant name.
+struct EnumDecoder<'a, R: 'a> {
+    outer: &'a mut Decoder<R>,
+}
+
+impl<'a, R: 'a> EnumDecoder<'a, R> {
+    fn new(outer: &'a mut Decoder<R>) -> Self {
+        EnumDecoder { outer }
+    }
+}
+
+impl<'de: 'a, 'a, R: Read<'de> + 'a> de::EnumAccess<'de> for EnumDecoder<'a, R> {
+    type Error = Error;
+    type Variant = VariantDecoder<'a, R>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let tag = self.outer.reader.read_bare_byte()?;
+
+        // An enum variant is encoded as a single-entry compound: a tag/name
+        // for the variant, its payload, then the closing 0x00. A 0x00 here,
+        // where the variant's own tag belongs, means the compound is empty
+        // and there's no variant to decode, rather than a name to read.
+        if tag == 0x00 {
+            return Err(Error::MalformedEnumVariant);
+        }
+
+        // TODO: Enforce that keys must be String. This is a bit of a hack,
+        // mirroring `MapDecoder::next_key_seed`.
+        let mut de = InnerDecoder {
+            outer: self.outer,
+            tag: 0x08,
+        };
+        let value = seed.deserialize(&mut de)?;
+
+        Ok((
+            value,
+            VariantDecoder {
+                outer: self.outer,
+                tag: tag as u8,
+            },
+        ))
+    }
+}
+
+/// Decoder for the payload of a single enum variant.
+struct VariantDecoder<'a, R: 'a> {
+    outer: &'a mut Decoder<R>,
+    tag: u8,
+}
+
+/// Consume the `0x00` tag that closes the single-entry compound wrapping an
+/// enum variant's payload. `EnumDecoder`/`VariantDecoder` only ever read the
+/// variant's name and value, leaving this end tag in the stream; without
+/// consuming it here, the next read (e.g. the enclosing `MapDecoder`'s next
+/// key) would see it instead and believe its own container had ended.
+///
+/// Finding anything other than `0x00` here means the compound had a second
+/// entry after the variant, which is malformed, not merely unread document
+/// trailing data — `Error::TrailingData` is reserved for `Decoder::end`'s
+/// whole-buffer check, so this is reported as `Error::MalformedEnumVariant`
+/// instead.
+fn expect_variant_end<'de, R: Read<'de>>(outer: &mut Decoder<R>) -> Result<()> {
+    match outer.reader.read_bare_byte()? {
+        0x00 => Ok(()),
+        _ => Err(Error::MalformedEnumVariant),
+    }
+}
+
+impl<'de: 'a, 'a, R: Read<'de> + 'a> de::VariantAccess<'de> for VariantDecoder<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        let mut de = InnerDecoder {
+            outer: &mut *self.outer,
+            tag: self.tag,
+        };
+        <de::IgnoredAny as de::Deserialize>::deserialize(&mut de)?;
+        expect_variant_end(self.outer)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let mut de = InnerDecoder {
+            outer: &mut *self.outer,
+            tag: self.tag,
+        };
+        let value = seed.deserialize(&mut de)?;
+        expect_variant_end(self.outer)?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.tag {
+            0x09 => {
+                let value = self
+                    .outer
+                    .with_nesting(|outer| visitor.visit_seq(SeqDecoder::list(outer)?))?;
+                expect_variant_end(self.outer)?;
+                Ok(value)
+            }
+            t => Err(de::Error::invalid_type(tag_to_unexpected(t), &visitor)),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.tag {
+            0x0a => {
+                let value = self
+                    .outer
+                    .with_nesting(|outer| visitor.visit_map(MapDecoder::new(outer)))?;
+                expect_variant_end(self.outer)?;
+                Ok(value)
+            }
+            t => Err(de::Error::invalid_type(tag_to_unexpected(t), &visitor)),
+        }
     }
 }